@@ -16,8 +16,8 @@ use revm::{db::BlockHashRef, primitives::SpecId};
 use crate::state::SyncState;
 
 use super::{
-    storage::ReservableSparseBlockchainStorage, validate_next_block, Blockchain, BlockchainError,
-    BlockchainMut,
+    storage::ReservableSparseBlockchainStorage, tree_route::TreeRoute, validate_next_block,
+    Blockchain, BlockchainError, BlockchainMut, ImportRoute, SnapshotId,
 };
 
 /// An error that occurs upon creation of a [`LocalBlockchain`].
@@ -43,6 +43,42 @@ pub enum InsertBlockError {
     MissingWithdrawals,
 }
 
+/// An error that occurs upon [`LocalBlockchain::insert_ancient_blocks`].
+#[derive(Debug, thiserror::Error)]
+pub enum InsertAncientBlockError {
+    /// The first block's parent isn't present in the blockchain
+    #[error("The first block's parent is not present in the blockchain")]
+    MissingParent,
+    /// The first block's parent isn't the current canonical head, so the fast bulk-import path
+    /// cannot be taken without risking clobbering canonical blocks
+    #[error("The first block's parent {actual} is not the current canonical head {expected}")]
+    NonCanonicalParent {
+        /// The first block's parent hash
+        actual: B256,
+        /// The current canonical head's hash
+        expected: B256,
+    },
+    /// Invalid block number
+    #[error("Invalid block number: {actual}. Expected: {expected}")]
+    InvalidBlockNumber {
+        /// Provided block number
+        actual: U256,
+        /// Expected block number
+        expected: U256,
+    },
+    /// Invalid parent hash
+    #[error("Block has invalid parent hash. Expected {expected} but got {actual}")]
+    InvalidParentHash {
+        /// Provided parent hash
+        actual: B256,
+        /// Expected parent hash
+        expected: B256,
+    },
+    /// Block failed full validation, only returned when `verify_ancient` is `true`
+    #[error(transparent)]
+    Validation(#[from] super::BlockValidationError),
+}
+
 /// A blockchain consisting of locally created blocks.
 #[derive(Debug)]
 pub struct LocalBlockchain {
@@ -168,6 +204,140 @@ impl LocalBlockchain {
             spec_id,
         }
     }
+
+    /// Computes the route connecting `from` and `to`, finding their common ancestor by walking
+    /// both blocks back to the height of the shorter side and then walking both back together
+    /// until their hashes match.
+    pub fn tree_route(&self, from: &B256, to: &B256) -> Result<TreeRoute, BlockchainError> {
+        let mut from_block = self
+            .storage
+            .block_by_hash(from)
+            .ok_or(BlockchainError::UnknownBlockHash)?;
+
+        let mut to_block = self
+            .storage
+            .block_by_hash(to)
+            .ok_or(BlockchainError::UnknownBlockHash)?;
+
+        let mut retracted = Vec::new();
+        let mut enacted = Vec::new();
+
+        while from_block.header.number > to_block.header.number {
+            let parent = self
+                .storage
+                .block_by_hash(&from_block.header.parent_hash)
+                .expect("Parent of a stored block must be stored");
+            retracted.push(std::mem::replace(&mut from_block, parent));
+        }
+
+        while to_block.header.number > from_block.header.number {
+            let parent = self
+                .storage
+                .block_by_hash(&to_block.header.parent_hash)
+                .expect("Parent of a stored block must be stored");
+            enacted.push(std::mem::replace(&mut to_block, parent));
+        }
+
+        while from_block.hash() != to_block.hash() {
+            let from_parent = self
+                .storage
+                .block_by_hash(&from_block.header.parent_hash)
+                .expect("Parent of a stored block must be stored");
+            retracted.push(std::mem::replace(&mut from_block, from_parent));
+
+            let to_parent = self
+                .storage
+                .block_by_hash(&to_block.header.parent_hash)
+                .expect("Parent of a stored block must be stored");
+            enacted.push(std::mem::replace(&mut to_block, to_parent));
+        }
+
+        enacted.reverse();
+
+        Ok(TreeRoute {
+            retracted,
+            common_ancestor: *from_block.hash(),
+            enacted,
+        })
+    }
+
+    /// Imports a contiguous range of already-trusted blocks in a single pass, bypassing the
+    /// per-block reorg bookkeeping performed by [`BlockchainMut::insert_block`]. Only the first
+    /// block's parent is looked up; every subsequent block is linked to its predecessor purely
+    /// by `parent_hash`/`number`, and total difficulty is accumulated incrementally.
+    ///
+    /// Since every block is written straight into canonical storage, the first block's parent
+    /// must be the current canonical head; otherwise this would silently clobber the existing
+    /// canonical chain instead of going through the reorg path in
+    /// [`BlockchainMut::insert_block`].
+    ///
+    /// When `verify_ancient` is `false`, no further validation is performed beyond linkage,
+    /// mirroring OpenEthereum's ancient-block import path for restoring an already-validated,
+    /// exported chain. When `true`, each block is additionally run through
+    /// [`validate_next_block`].
+    pub fn insert_ancient_blocks(
+        &mut self,
+        blocks: impl Iterator<Item = DetailedBlock>,
+        verify_ancient: bool,
+    ) -> Result<(), InsertAncientBlockError> {
+        let mut blocks = blocks.peekable();
+
+        let Some(first_block) = blocks.peek() else {
+            return Ok(());
+        };
+
+        let mut last_block = self
+            .storage
+            .block_by_hash(&first_block.header.parent_hash)
+            .ok_or(InsertAncientBlockError::MissingParent)?;
+
+        let current_head = self
+            .storage
+            .block_by_number(self.storage.last_block_number())
+            .expect("Head block must exist");
+
+        if last_block.hash() != current_head.hash() {
+            return Err(InsertAncientBlockError::NonCanonicalParent {
+                actual: *last_block.hash(),
+                expected: *current_head.hash(),
+            });
+        }
+
+        let mut total_difficulty = self
+            .storage
+            .total_difficulty_by_hash(last_block.hash())
+            .expect("Must exist as its block is stored");
+
+        for block in blocks {
+            let expected_number = last_block.header.number + U256::from(1);
+            if block.header.number != expected_number {
+                return Err(InsertAncientBlockError::InvalidBlockNumber {
+                    actual: block.header.number,
+                    expected: expected_number,
+                });
+            }
+
+            if block.header.parent_hash != *last_block.hash() {
+                return Err(InsertAncientBlockError::InvalidParentHash {
+                    actual: block.header.parent_hash,
+                    expected: *last_block.hash(),
+                });
+            }
+
+            if verify_ancient {
+                validate_next_block(self.spec_id, &last_block, &block)?;
+            }
+
+            total_difficulty += block.header.difficulty;
+
+            // SAFETY: Number/hash linkage was just checked above, so the block's number and
+            // hash are unique.
+            last_block =
+                unsafe { self.storage.insert_block_unchecked(block, total_difficulty) }.clone();
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -231,26 +401,73 @@ impl Blockchain for LocalBlockchain {
 impl BlockchainMut for LocalBlockchain {
     type Error = BlockchainError;
 
-    async fn insert_block(
-        &mut self,
-        block: DetailedBlock,
-    ) -> Result<Arc<DetailedBlock>, Self::Error> {
-        let last_block = self.last_block().await?;
+    async fn insert_block(&mut self, block: DetailedBlock) -> Result<ImportRoute, Self::Error> {
+        let parent_block = self
+            .storage
+            .block_by_hash(&block.header.parent_hash)
+            .ok_or(BlockchainError::UnknownBlockHash)?;
 
-        validate_next_block(self.spec_id, &last_block, &block)?;
+        validate_next_block(self.spec_id, &parent_block, &block)?;
 
-        let previous_total_difficulty = self
-            .total_difficulty_by_hash(last_block.hash())
+        let parent_total_difficulty = self
+            .storage
+            .total_difficulty_by_hash(parent_block.hash())
+            .expect("Must exist as its block is stored");
+
+        let total_difficulty = parent_total_difficulty + block.header.difficulty;
+        let block_hash = *block.hash();
+
+        // SAFETY: The block has just been validated against its parent, and its hash is
+        // derived from its contents, so a stored block with the same hash (e.g. a re-imported
+        // head) is identical and is merely replaced.
+        unsafe {
+            self.storage
+                .insert_side_chain_block_unchecked(block, total_difficulty);
+        }
+
+        let current_head = self.last_block().await?;
+
+        if current_head.hash() == &block_hash {
+            return Ok(ImportRoute {
+                retracted: Vec::new(),
+                enacted: Vec::new(),
+            });
+        }
+
+        let current_head_total_difficulty = self
+            .total_difficulty_by_hash(current_head.hash())
             .await
             .expect("No error can occur as it is stored locally")
             .expect("Must exist as its block is stored");
 
-        let total_difficulty = previous_total_difficulty + block.header.difficulty;
+        if total_difficulty <= current_head_total_difficulty {
+            // The new block doesn't overtake the current head, so it remains a side-chain block.
+            return Ok(ImportRoute {
+                retracted: Vec::new(),
+                enacted: Vec::new(),
+            });
+        }
 
-        // SAFETY: The block number is guaranteed to be unique, so the block hash must be too.
-        let block = unsafe { self.storage.insert_block_unchecked(block, total_difficulty) };
+        let tree_route = self.tree_route(current_head.hash(), &block_hash)?;
+
+        for retracted in &tree_route.retracted {
+            self.storage.retract_canonical(&retracted.header.number);
+        }
 
-        Ok(block.clone())
+        for enacted in &tree_route.enacted {
+            self.storage.make_canonical(enacted.hash());
+        }
+
+        self.storage.set_last_block_number(
+            tree_route
+                .enacted
+                .last()
+                .expect("A reorg always enacts at least the newly inserted block")
+                .header
+                .number,
+        );
+
+        Ok(tree_route.into())
     }
 
     async fn reserve_blocks(
@@ -289,6 +506,18 @@ impl BlockchainMut for LocalBlockchain {
             Err(BlockchainError::UnknownBlockNumber)
         }
     }
+
+    async fn snapshot(&mut self) -> SnapshotId {
+        self.storage.snapshot()
+    }
+
+    async fn revert_to_snapshot(&mut self, snapshot_id: SnapshotId) -> Result<(), Self::Error> {
+        if self.storage.revert_to_snapshot(snapshot_id) {
+            Ok(())
+        } else {
+            Err(BlockchainError::UnknownSnapshot)
+        }
+    }
 }
 
 impl BlockHashRef for LocalBlockchain {
@@ -301,3 +530,358 @@ impl BlockHashRef for LocalBlockchain {
             .ok_or(BlockchainError::UnknownBlockNumber)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genesis_block() -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                number: U256::ZERO,
+                difficulty: U256::from(1),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    fn child_block(parent: &DetailedBlock, difficulty: U256, extra_data: &[u8]) -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                parent_hash: *parent.hash(),
+                number: parent.header.number + U256::from(1),
+                difficulty,
+                extra_data: Bytes::from(extra_data.to_vec()),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    fn new_blockchain() -> LocalBlockchain {
+        LocalBlockchain::with_genesis_block(U256::from(1), SpecId::BERLIN, genesis_block())
+            .expect("genesis block is valid")
+    }
+
+    #[tokio::test]
+    async fn insert_ancient_blocks_imports_a_contiguous_range_and_accumulates_difficulty() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let block1 = child_block(&genesis, U256::from(2), b"block1");
+        let block1_hash = *block1.hash();
+        let block2 = child_block(&block1, U256::from(3), b"block2");
+        let block2_hash = *block2.hash();
+        let block3 = child_block(&block2, U256::from(5), b"block3");
+        let block3_hash = *block3.hash();
+
+        blockchain
+            .insert_ancient_blocks(vec![block1, block2, block3].into_iter(), false)
+            .unwrap();
+
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &block3_hash);
+        assert_eq!(
+            blockchain
+                .block_by_number(&U256::from(1))
+                .await
+                .unwrap()
+                .unwrap()
+                .hash(),
+            &block1_hash
+        );
+        assert_eq!(
+            blockchain
+                .block_by_number(&U256::from(2))
+                .await
+                .unwrap()
+                .unwrap()
+                .hash(),
+            &block2_hash
+        );
+
+        // Genesis (difficulty 1) + 2 + 3 + 5.
+        assert_eq!(
+            blockchain
+                .total_difficulty_by_hash(&block3_hash)
+                .await
+                .unwrap(),
+            Some(U256::from(11))
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_ancient_blocks_rejects_an_unknown_parent() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let orphan_parent = child_block(&genesis, U256::from(1), b"never-inserted");
+        let orphan = child_block(&orphan_parent, U256::from(1), b"orphan");
+
+        let result = blockchain.insert_ancient_blocks(std::iter::once(orphan), false);
+
+        assert!(matches!(
+            result,
+            Err(InsertAncientBlockError::MissingParent)
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_ancient_blocks_rejects_a_parent_that_is_not_the_canonical_head() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+        let genesis_hash = *genesis.hash();
+
+        let block1 = child_block(&genesis, U256::from(1), b"block1");
+        blockchain.insert_block(block1.clone()).await.unwrap();
+
+        let block2 = child_block(&block1, U256::from(1), b"block2");
+        blockchain.insert_block(block2).await.unwrap();
+
+        // The canonical head has since moved on to `block2`, so an ancient import chained off
+        // the now-stale `genesis` hash must be rejected rather than silently clobbering the
+        // canonical chain.
+        let ancient = child_block(&genesis, U256::from(1), b"ancient-child");
+        let result = blockchain.insert_ancient_blocks(std::iter::once(ancient), false);
+
+        assert!(matches!(
+            result,
+            Err(InsertAncientBlockError::NonCanonicalParent { actual, .. })
+                if actual == genesis_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_ancient_blocks_rejects_a_gap_in_block_numbers() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let block1 = child_block(&genesis, U256::from(1), b"block1");
+        let mut block3 = child_block(&block1, U256::from(1), b"block3");
+        block3.header.number = U256::from(3);
+
+        let result = blockchain.insert_ancient_blocks(vec![block1, block3].into_iter(), false);
+
+        assert!(matches!(
+            result,
+            Err(InsertAncientBlockError::InvalidBlockNumber {
+                actual,
+                expected,
+            }) if actual == U256::from(3) && expected == U256::from(2)
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_ancient_blocks_rejects_a_mismatched_parent_hash() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let block1 = child_block(&genesis, U256::from(1), b"block1");
+        // Not actually chained onto `block1`.
+        let mut block2 = child_block(&genesis, U256::from(1), b"unrelated-block2");
+        block2.header.number = block1.header.number + U256::from(1);
+        let expected_parent_hash = *block1.hash();
+
+        let result = blockchain.insert_ancient_blocks(vec![block1, block2].into_iter(), false);
+
+        assert!(matches!(
+            result,
+            Err(InsertAncientBlockError::InvalidParentHash { expected, .. })
+                if expected == expected_parent_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_ancient_blocks_with_verify_ancient_runs_full_validation() {
+        // `insert_ancient_blocks`'s own linkage checks don't look at `base_fee`; only
+        // `validate_next_block`, invoked when `verify_ancient` is `true`, does.
+        let genesis =
+            LocalBlockchain::with_genesis_block(U256::from(1), SpecId::LONDON, genesis_block())
+                .unwrap()
+                .last_block()
+                .await
+                .unwrap();
+
+        // `child_block` doesn't set `base_fee`, so this block is invalid on a post-London spec.
+        let missing_base_fee = child_block(&genesis, U256::from(1), b"missing-base-fee");
+
+        let mut without_verification =
+            LocalBlockchain::with_genesis_block(U256::from(1), SpecId::LONDON, genesis_block())
+                .unwrap();
+        assert!(without_verification
+            .insert_ancient_blocks(std::iter::once(missing_base_fee.clone()), false)
+            .is_ok());
+
+        let mut with_verification =
+            LocalBlockchain::with_genesis_block(U256::from(1), SpecId::LONDON, genesis_block())
+                .unwrap();
+        let result =
+            with_verification.insert_ancient_blocks(std::iter::once(missing_base_fee), true);
+
+        assert!(matches!(
+            result,
+            Err(InsertAncientBlockError::Validation(
+                super::super::BlockValidationError::MissingBaseFee
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_block_reorgs_to_heavier_side_chain() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let light = child_block(&genesis, U256::from(1), b"light");
+        let light_hash = *light.hash();
+        blockchain.insert_block(light).await.unwrap();
+
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &light_hash);
+
+        let heavy = child_block(&genesis, U256::from(2), b"heavy");
+        let heavy_hash = *heavy.hash();
+        let route = blockchain.insert_block(heavy).await.unwrap();
+
+        assert_eq!(route.retracted.len(), 1);
+        assert_eq!(route.retracted[0].hash(), &light_hash);
+        assert_eq!(route.enacted.len(), 1);
+        assert_eq!(route.enacted[0].hash(), &heavy_hash);
+
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &heavy_hash);
+        assert_eq!(
+            blockchain
+                .block_by_number(&U256::from(1))
+                .await
+                .unwrap()
+                .unwrap()
+                .hash(),
+            &heavy_hash
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_block_keeps_tied_side_chain_off_the_canonical_chain() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let first = child_block(&genesis, U256::from(2), b"first");
+        let first_hash = *first.hash();
+        blockchain.insert_block(first).await.unwrap();
+
+        let tied = child_block(&genesis, U256::from(2), b"tied");
+        let route = blockchain.insert_block(tied).await.unwrap();
+
+        assert!(route.retracted.is_empty());
+        assert!(route.enacted.is_empty());
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &first_hash);
+    }
+
+    #[tokio::test]
+    async fn insert_block_short_circuits_on_duplicate_head() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let block = child_block(&genesis, U256::from(1), b"only");
+        blockchain.insert_block(block.clone()).await.unwrap();
+
+        let route = blockchain.insert_block(block).await.unwrap();
+
+        assert!(route.retracted.is_empty());
+        assert!(route.enacted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn tree_route_finds_common_ancestor_across_uneven_branch_lengths() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let short_branch = child_block(&genesis, U256::from(1), b"short-1");
+        let short_branch_hash = *short_branch.hash();
+        blockchain.insert_block(short_branch).await.unwrap();
+
+        let long_branch_1 = child_block(&genesis, U256::from(1), b"long-1");
+        blockchain
+            .insert_block(long_branch_1.clone())
+            .await
+            .unwrap();
+        let long_branch_2 = child_block(&long_branch_1, U256::from(1), b"long-2");
+        let long_branch_2_hash = *long_branch_2.hash();
+        blockchain.insert_block(long_branch_2).await.unwrap();
+
+        let route = blockchain
+            .tree_route(&short_branch_hash, &long_branch_2_hash)
+            .unwrap();
+
+        assert_eq!(route.common_ancestor, *genesis.hash());
+        assert_eq!(route.retracted.len(), 1);
+        assert_eq!(route.retracted[0].hash(), &short_branch_hash);
+        assert_eq!(route.enacted.len(), 2);
+        assert_eq!(route.enacted[1].hash(), &long_branch_2_hash);
+    }
+
+    #[tokio::test]
+    async fn revert_to_snapshot_discards_blocks_inserted_after_the_snapshot() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let block1 = child_block(&genesis, U256::from(1), b"block1");
+        blockchain.insert_block(block1.clone()).await.unwrap();
+
+        let snapshot_id = blockchain.snapshot().await;
+
+        let block2 = child_block(&block1, U256::from(1), b"block2");
+        blockchain.insert_block(block2).await.unwrap();
+        assert_eq!(
+            blockchain.last_block().await.unwrap().header.number,
+            U256::from(2)
+        );
+
+        blockchain.revert_to_snapshot(snapshot_id).await.unwrap();
+
+        assert_eq!(
+            blockchain.last_block().await.unwrap().header.number,
+            U256::from(1)
+        );
+        assert!(blockchain
+            .block_by_number(&U256::from(2))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn revert_to_snapshot_undoes_a_reorg_that_happened_after_it_was_taken() {
+        let mut blockchain = new_blockchain();
+        let genesis = blockchain.last_block().await.unwrap();
+
+        let light = child_block(&genesis, U256::from(1), b"light");
+        let light_hash = *light.hash();
+        blockchain.insert_block(light).await.unwrap();
+
+        let snapshot_id = blockchain.snapshot().await;
+
+        let heavy = child_block(&genesis, U256::from(2), b"heavy");
+        blockchain.insert_block(heavy).await.unwrap();
+        assert_ne!(blockchain.last_block().await.unwrap().hash(), &light_hash);
+
+        blockchain.revert_to_snapshot(snapshot_id).await.unwrap();
+
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &light_hash);
+    }
+
+    #[tokio::test]
+    async fn revert_to_snapshot_fails_for_an_unknown_snapshot() {
+        let mut blockchain = new_blockchain();
+
+        let error = blockchain.revert_to_snapshot(12345).await.unwrap_err();
+
+        assert!(matches!(error, BlockchainError::UnknownSnapshot));
+    }
+}
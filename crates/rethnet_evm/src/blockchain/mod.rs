@@ -0,0 +1,173 @@
+mod local;
+mod queue;
+mod storage;
+#[cfg(feature = "test-utils")]
+mod test_blockchain;
+mod tree_route;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rethnet_eth::{block::DetailedBlock, receipt::BlockReceipt, B256, U256};
+use revm::primitives::SpecId;
+
+pub use self::local::{CreationError, InsertBlockError, LocalBlockchain};
+pub use self::queue::{BlockQueue, QueueInfo, QueueVerificationError, VerificationResult};
+pub use self::storage::{ReservableSparseBlockchainStorage, SnapshotId, SparseBlockchainStorage};
+#[cfg(feature = "test-utils")]
+pub use self::test_blockchain::TestBlockchain;
+pub use self::tree_route::{ImportRoute, TreeRoute};
+
+/// Trait for reading blockchain data.
+#[async_trait]
+pub trait Blockchain {
+    /// The blockchain's error type
+    type Error;
+
+    /// Retrieves the block with the provided hash, if it exists.
+    async fn block_by_hash(&self, hash: &B256) -> Result<Option<Arc<DetailedBlock>>, Self::Error>;
+
+    /// Retrieves the block with the provided number, if it exists.
+    async fn block_by_number(
+        &self,
+        number: &U256,
+    ) -> Result<Option<Arc<DetailedBlock>>, Self::Error>;
+
+    /// Retrieves the block that contains the provided transaction hash, if it exists.
+    async fn block_by_transaction_hash(
+        &self,
+        transaction_hash: &B256,
+    ) -> Result<Option<Arc<DetailedBlock>>, Self::Error>;
+
+    /// Whether the block at the provided number supports the specified specification.
+    async fn block_supports_spec(
+        &self,
+        number: &U256,
+        spec_id: SpecId,
+    ) -> Result<bool, Self::Error>;
+
+    /// Returns the chain ID of the blockchain.
+    async fn chain_id(&self) -> U256;
+
+    /// Retrieves the last block in the blockchain.
+    async fn last_block(&self) -> Result<Arc<DetailedBlock>, Self::Error>;
+
+    /// Retrieves the number of the last block in the blockchain.
+    async fn last_block_number(&self) -> U256;
+
+    /// Retrieves the receipt of the transaction with the provided hash, if it exists.
+    async fn receipt_by_transaction_hash(
+        &self,
+        transaction_hash: &B256,
+    ) -> Result<Option<Arc<BlockReceipt>>, Self::Error>;
+
+    /// Retrieves the total difficulty at the block with the provided hash, if it exists.
+    async fn total_difficulty_by_hash(&self, hash: &B256) -> Result<Option<U256>, Self::Error>;
+}
+
+/// Trait for mutating blockchain data.
+#[async_trait]
+pub trait BlockchainMut {
+    /// The blockchain's error type
+    type Error;
+
+    /// Inserts the provided block into the blockchain. If the block's total difficulty exceeds
+    /// that of the current head, the canonical chain is reorganized to adopt it, and the
+    /// returned [`ImportRoute`] describes the blocks that were retracted and enacted.
+    async fn insert_block(&mut self, block: DetailedBlock) -> Result<ImportRoute, Self::Error>;
+
+    /// Reserves the provided number of blocks, starting after the last block, with the provided
+    /// interval between timestamps.
+    async fn reserve_blocks(
+        &mut self,
+        additional: usize,
+        interval: U256,
+    ) -> Result<(), Self::Error>;
+
+    /// Reverts to the block with the provided number, discarding all later blocks.
+    async fn revert_to_block(&mut self, block_number: &U256) -> Result<(), Self::Error>;
+
+    /// Takes a snapshot of the current chain head, returning an identifier that can later be
+    /// passed to [`BlockchainMut::revert_to_snapshot`] to discard all blocks inserted since.
+    async fn snapshot(&mut self) -> SnapshotId;
+
+    /// Reverts the blockchain to the state it was in when the snapshot with the provided id was
+    /// taken, discarding all blocks inserted since.
+    async fn revert_to_snapshot(&mut self, snapshot_id: SnapshotId) -> Result<(), Self::Error>;
+}
+
+/// An error that occurs when validating a block against its predecessor.
+#[derive(Debug, thiserror::Error)]
+pub enum BlockValidationError {
+    /// Block has a non-increasing block number
+    #[error("Block number has non-increasing number. Expected {expected} but got {actual}")]
+    InvalidBlockNumber {
+        /// Provided block number
+        actual: U256,
+        /// Expected block number
+        expected: U256,
+    },
+    /// Block has a parent hash that doesn't match the previous block's hash
+    #[error("Block has invalid parent hash. Expected {expected} but got {actual}")]
+    InvalidParentHash {
+        /// Provided parent hash
+        actual: B256,
+        /// Expected parent hash
+        expected: B256,
+    },
+    /// Missing base fee per gas for post-London blockchain
+    #[error("Missing base fee per gas for post-London blockchain")]
+    MissingBaseFee,
+    /// Missing withdrawals for post-Shanghai blockchain
+    #[error("Missing withdrawals for post-Shanghai blockchain")]
+    MissingWithdrawals,
+}
+
+/// An error that occurs when querying or mutating a blockchain.
+#[derive(Debug, thiserror::Error)]
+pub enum BlockchainError {
+    /// Block validation error
+    #[error(transparent)]
+    BlockValidation(#[from] BlockValidationError),
+    /// The block number doesn't exist in the blockchain
+    #[error("Unknown block number")]
+    UnknownBlockNumber,
+    /// The block hash doesn't exist in the blockchain
+    #[error("Unknown block hash")]
+    UnknownBlockHash,
+    /// The snapshot id doesn't correspond to a known snapshot
+    #[error("Unknown snapshot")]
+    UnknownSnapshot,
+}
+
+/// Validates that `next_block` can be appended to `last_block`.
+pub(crate) fn validate_next_block(
+    spec_id: SpecId,
+    last_block: &DetailedBlock,
+    next_block: &DetailedBlock,
+) -> Result<(), BlockValidationError> {
+    let next_block_number = last_block.header.number + U256::from(1);
+    if next_block.header.number != next_block_number {
+        return Err(BlockValidationError::InvalidBlockNumber {
+            actual: next_block.header.number,
+            expected: next_block_number,
+        });
+    }
+
+    if next_block.header.parent_hash != *last_block.hash() {
+        return Err(BlockValidationError::InvalidParentHash {
+            actual: next_block.header.parent_hash,
+            expected: *last_block.hash(),
+        });
+    }
+
+    if spec_id >= SpecId::LONDON && next_block.header.base_fee_per_gas.is_none() {
+        return Err(BlockValidationError::MissingBaseFee);
+    }
+
+    if spec_id >= SpecId::SHANGHAI && next_block.header.withdrawals_root.is_none() {
+        return Err(BlockValidationError::MissingWithdrawals);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,503 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use rethnet_eth::{block::DetailedBlock, trie, utils::keccak256, B256, U256};
+use revm::primitives::SpecId;
+use tokio::sync::{mpsc, Mutex, Notify};
+
+/// The Keccak-256 hash of the RLP encoding of an empty list, i.e. the `ommersHash` of a block
+/// with no ommers.
+const EMPTY_OMMERS_HASH: B256 = B256::new([
+    0x1d, 0xcc, 0x4d, 0xe8, 0xde, 0xc7, 0x5d, 0x7a, 0xab, 0x85, 0xb5, 0x67, 0xb6, 0xcc, 0xd4, 0x1a,
+    0xd3, 0x12, 0x45, 0x1b, 0x94, 0x8a, 0x74, 0x13, 0xf0, 0xa1, 0x42, 0xfd, 0x40, 0xd4, 0x93, 0x47,
+]);
+
+/// A snapshot of how many blocks are at each stage of a [`BlockQueue`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueInfo {
+    /// The number of blocks that have been enqueued but not yet picked up by a verifier.
+    pub unverified_queue_size: usize,
+    /// The number of blocks currently undergoing stateless verification.
+    pub verifying_queue_size: usize,
+    /// The number of blocks whose verification has finished, passed or failed, including those
+    /// held back until all earlier-enqueued blocks have finished, so that draining preserves
+    /// insertion order.
+    pub verified_queue_size: usize,
+}
+
+impl QueueInfo {
+    /// The total number of blocks known to the queue, at any stage.
+    pub fn total(&self) -> usize {
+        self.unverified_queue_size + self.verifying_queue_size + self.verified_queue_size
+    }
+
+    /// Whether the queue still has unverified or in-progress blocks.
+    pub fn incomplete(&self) -> bool {
+        self.unverified_queue_size > 0 || self.verifying_queue_size > 0
+    }
+}
+
+/// An error produced while stateless-verifying a block enqueued in a [`BlockQueue`].
+#[derive(Debug, thiserror::Error)]
+pub enum QueueVerificationError {
+    /// Block has a non-increasing block number
+    #[error("Invalid block number: {actual}. Expected: {expected}")]
+    InvalidBlockNumber {
+        /// Provided block number
+        actual: U256,
+        /// Expected block number
+        expected: U256,
+    },
+    /// Block has a parent hash that doesn't match the previous block's hash
+    #[error("Block has invalid parent hash. Expected {expected} but got {actual}")]
+    InvalidParentHash {
+        /// Provided parent hash
+        actual: B256,
+        /// Expected parent hash
+        expected: B256,
+    },
+    /// Missing base fee per gas for post-London blockchain
+    #[error("Missing base fee per gas for post-London blockchain")]
+    MissingBaseFee,
+    /// Missing withdrawals root for post-Shanghai blockchain
+    #[error("Missing withdrawals root for post-Shanghai blockchain")]
+    MissingWithdrawalsRoot,
+    /// Missing prevrandao for post-merge blockchain
+    #[error("Missing prevrandao for post-merge blockchain")]
+    MissingPrevrandao,
+    /// Transactions root doesn't match the recomputed root
+    #[error("Transactions root {actual} does not match computed root {expected}")]
+    InvalidTransactionsRoot {
+        /// Provided transactions root
+        actual: B256,
+        /// Recomputed transactions root
+        expected: B256,
+    },
+    /// Ommers hash doesn't match the recomputed hash
+    #[error("Ommers hash {actual} does not match computed hash {expected}")]
+    InvalidOmmersHash {
+        /// Provided ommers hash
+        actual: B256,
+        /// Recomputed ommers hash
+        expected: B256,
+    },
+}
+
+/// Performs the stateless checks that don't require chain state: structural self-consistency of
+/// the block, and the presence of the fields required by `spec_id`. Linkage to the previous
+/// block (number continuity, parent hash) is checked separately by the caller, as it concerns
+/// the relationship between blocks rather than a single block in isolation.
+fn verify_block_structure(
+    block: &DetailedBlock,
+    spec_id: SpecId,
+) -> Result<(), QueueVerificationError> {
+    if spec_id >= SpecId::LONDON && block.header.base_fee_per_gas.is_none() {
+        return Err(QueueVerificationError::MissingBaseFee);
+    }
+
+    if spec_id >= SpecId::SHANGHAI && block.header.withdrawals_root.is_none() {
+        return Err(QueueVerificationError::MissingWithdrawalsRoot);
+    }
+
+    if spec_id >= SpecId::MERGE && block.header.mix_hash == B256::zero() {
+        return Err(QueueVerificationError::MissingPrevrandao);
+    }
+
+    let computed_transactions_root = trie::ordered_trie_root(
+        block
+            .transactions
+            .iter()
+            .map(|transaction| rlp::encode(transaction).to_vec()),
+    );
+    if block.header.transactions_root != computed_transactions_root {
+        return Err(QueueVerificationError::InvalidTransactionsRoot {
+            actual: block.header.transactions_root,
+            expected: computed_transactions_root,
+        });
+    }
+
+    let computed_ommers_hash = if block.ommers.is_empty() {
+        EMPTY_OMMERS_HASH
+    } else {
+        keccak256(&rlp::encode_list(&block.ommers))
+    };
+    if block.header.ommers_hash != computed_ommers_hash {
+        return Err(QueueVerificationError::InvalidOmmersHash {
+            actual: block.header.ommers_hash,
+            expected: computed_ommers_hash,
+        });
+    }
+
+    Ok(())
+}
+
+struct Sequenced {
+    sequence: u64,
+    hash: B256,
+    block: DetailedBlock,
+}
+
+/// The outcome of stateless-verifying a block: the block itself if it passed, or the reason it
+/// failed.
+pub type VerificationResult = Result<DetailedBlock, QueueVerificationError>;
+
+/// A parallel block-import queue, modeled on OpenEthereum's three-stage block queue: blocks are
+/// enqueued as `unverified`, stateless-verified concurrently by a pool of worker tasks, and
+/// handed off to a `verified` queue that preserves the original insertion order so that serial
+/// import via [`super::BlockchainMut::insert_block`] stays deterministic.
+///
+/// This type only covers the concurrent, stateless-verification half of the pipeline described
+/// above: `enqueue`, `drain_verified` and `wait_for_verified` give an importer task everything it
+/// needs to wake only when verified blocks are ready and feed them to `insert_block` in order.
+/// Driving that importer task — spawning it, and actually calling `insert_block` for each drained
+/// block — is left to the caller; `BlockQueue` itself is not wired to any `BlockchainMut`
+/// implementation.
+pub struct BlockQueue {
+    next_sequence: u64,
+    last_enqueued: Option<(U256, B256)>,
+    in_flight: Arc<std::sync::Mutex<HashSet<B256>>>,
+    unverified_sender: mpsc::Sender<Sequenced>,
+    unverified_count: Arc<AtomicUsize>,
+    verifying_count: Arc<AtomicUsize>,
+    pending: Arc<Mutex<BTreeMap<u64, VerificationResult>>>,
+    next_to_release: Arc<std::sync::atomic::AtomicU64>,
+    verified: Arc<Mutex<VecDeque<VerificationResult>>>,
+    ready: Arc<Notify>,
+    workers: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl BlockQueue {
+    /// Constructs a new queue that verifies blocks against the given [`SpecId`], using
+    /// `max(num_cpus::get(), 3) - 2` worker tasks.
+    pub fn new(spec_id: SpecId) -> Self {
+        let num_workers = num_cpus::get().max(3) - 2;
+
+        let (unverified_sender, unverified_receiver) = mpsc::channel::<Sequenced>(1024);
+        let unverified_receiver = Arc::new(Mutex::new(unverified_receiver));
+
+        let in_flight = Arc::new(std::sync::Mutex::new(HashSet::new()));
+        let unverified_count = Arc::new(AtomicUsize::new(0));
+        let verifying_count = Arc::new(AtomicUsize::new(0));
+        let pending = Arc::new(Mutex::new(BTreeMap::new()));
+        let next_to_release = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let verified = Arc::new(Mutex::new(VecDeque::new()));
+        let ready = Arc::new(Notify::new());
+
+        let workers = (0..num_workers)
+            .map(|_| {
+                let unverified_receiver = unverified_receiver.clone();
+                let unverified_count = unverified_count.clone();
+                let verifying_count = verifying_count.clone();
+                let pending = pending.clone();
+                let next_to_release = next_to_release.clone();
+                let verified = verified.clone();
+                let ready = ready.clone();
+                let in_flight = in_flight.clone();
+
+                tokio::spawn(async move {
+                    loop {
+                        let sequenced = {
+                            let mut receiver = unverified_receiver.lock().await;
+                            receiver.recv().await
+                        };
+
+                        let Some(Sequenced {
+                            sequence,
+                            hash,
+                            block,
+                        }) = sequenced
+                        else {
+                            break;
+                        };
+
+                        verifying_count.fetch_add(1, Ordering::SeqCst);
+                        unverified_count.fetch_sub(1, Ordering::SeqCst);
+
+                        let result = verify_block_structure(&block, spec_id).map(|()| block);
+
+                        // The block's verification has finished, so it's no longer
+                        // in flight: a caller is now free to re-`enqueue` the same hash (e.g.
+                        // after observing it was rejected).
+                        in_flight.lock().expect("lock isn't poisoned").remove(&hash);
+
+                        let mut pending = pending.lock().await;
+                        pending.insert(sequence, result);
+
+                        let mut verified = verified.lock().await;
+                        while let Some(result) =
+                            pending.remove(&next_to_release.load(Ordering::SeqCst))
+                        {
+                            verified.push_back(result);
+                            next_to_release.fetch_add(1, Ordering::SeqCst);
+                        }
+                        drop(pending);
+                        drop(verified);
+
+                        // Only counted as done once its result is in `pending` or `verified`.
+                        verifying_count.fetch_sub(1, Ordering::SeqCst);
+
+                        ready.notify_waiters();
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            next_sequence: 0,
+            last_enqueued: None,
+            in_flight,
+            unverified_sender,
+            unverified_count,
+            verifying_count,
+            pending,
+            next_to_release,
+            verified,
+            ready,
+            workers,
+        }
+    }
+
+    /// Enqueues a block for verification. Blocks already in flight (by hash) are silently
+    /// deduplicated. The block-number/parent-hash linkage against the previously enqueued block
+    /// is checked eagerly, as it is cheap and lets callers fail fast on an out-of-order feed.
+    pub async fn enqueue(&mut self, block: DetailedBlock) -> Result<(), QueueVerificationError> {
+        let hash = *block.hash();
+
+        {
+            let mut in_flight = self.in_flight.lock().expect("lock isn't poisoned");
+            if !in_flight.insert(hash) {
+                return Ok(());
+            }
+        }
+
+        if let Some((last_number, last_hash)) = self.last_enqueued {
+            let expected_number = last_number + U256::from(1);
+            if block.header.number != expected_number {
+                self.in_flight
+                    .lock()
+                    .expect("lock isn't poisoned")
+                    .remove(&hash);
+
+                return Err(QueueVerificationError::InvalidBlockNumber {
+                    actual: block.header.number,
+                    expected: expected_number,
+                });
+            }
+
+            if block.header.parent_hash != last_hash {
+                self.in_flight
+                    .lock()
+                    .expect("lock isn't poisoned")
+                    .remove(&hash);
+
+                return Err(QueueVerificationError::InvalidParentHash {
+                    actual: block.header.parent_hash,
+                    expected: last_hash,
+                });
+            }
+        }
+
+        self.last_enqueued = Some((block.header.number, hash));
+
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        self.unverified_count.fetch_add(1, Ordering::SeqCst);
+
+        self.unverified_sender
+            .send(Sequenced {
+                sequence,
+                hash,
+                block,
+            })
+            .await
+            .expect("Worker tasks outlive the queue");
+
+        Ok(())
+    }
+
+    /// Drains all currently available verified blocks, in their original insertion order.
+    pub async fn drain_verified(&self) -> Vec<VerificationResult> {
+        let mut verified = self.verified.lock().await;
+        verified.drain(..).collect()
+    }
+
+    /// Waits until at least one verified block is available to drain.
+    pub async fn wait_for_verified(&self) {
+        loop {
+            // Created before the check, so a notification sent in between isn't missed.
+            let notified = self.ready.notified();
+
+            if !self.verified.lock().await.is_empty() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+
+    /// Returns a snapshot of the number of blocks at each stage of the queue.
+    pub async fn info(&self) -> QueueInfo {
+        QueueInfo {
+            unverified_queue_size: self.unverified_count.load(Ordering::SeqCst),
+            verifying_queue_size: self.verifying_count.load(Ordering::SeqCst),
+            verified_queue_size: self.pending.lock().await.len() + self.verified.lock().await.len(),
+        }
+    }
+}
+
+impl Drop for BlockQueue {
+    fn drop(&mut self) {
+        for worker in &self.workers {
+            worker.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rethnet_eth::block::{Block, PartialHeader};
+
+    use super::*;
+
+    fn genesis_block() -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                number: U256::ZERO,
+                difficulty: U256::from(1),
+                base_fee: Some(U256::from(1)),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    fn child_block(parent: &DetailedBlock) -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                parent_hash: *parent.hash(),
+                number: parent.header.number + U256::from(1),
+                difficulty: U256::from(1),
+                base_fee: Some(U256::from(1)),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    #[tokio::test]
+    async fn verified_blocks_drain_in_insertion_order() {
+        let mut queue = BlockQueue::new(SpecId::LONDON);
+
+        let genesis = genesis_block();
+        let block1 = child_block(&genesis);
+        let block1_hash = *block1.hash();
+        let block2 = child_block(&block1);
+        let block2_hash = *block2.hash();
+
+        queue.enqueue(block1).await.unwrap();
+        queue.enqueue(block2).await.unwrap();
+
+        queue.wait_for_verified().await;
+        // Give the worker verifying the second block a chance to also reach `verified`, since
+        // `wait_for_verified` only guarantees the queue is non-empty.
+        while queue.info().await.incomplete() {
+            tokio::task::yield_now().await;
+        }
+
+        let verified = queue.drain_verified().await;
+        let hashes: Vec<_> = verified
+            .into_iter()
+            .map(|result| *result.expect("blocks are well-formed").hash())
+            .collect();
+
+        assert_eq!(hashes, vec![block1_hash, block2_hash]);
+    }
+
+    #[tokio::test]
+    async fn enqueue_rejects_a_gap_in_block_numbers_and_releases_the_hash() {
+        let mut queue = BlockQueue::new(SpecId::LONDON);
+
+        let genesis = genesis_block();
+        let block1 = child_block(&genesis);
+        queue.enqueue(block1).await.unwrap();
+
+        // Skips straight to block 3, leaving a gap after block 1.
+        let skipped = DetailedBlock::new(
+            Block::new(
+                PartialHeader {
+                    parent_hash: *genesis.hash(),
+                    number: U256::from(3),
+                    difficulty: U256::from(1),
+                    base_fee: Some(U256::from(1)),
+                    ..PartialHeader::default()
+                },
+                Vec::new(),
+                Vec::new(),
+                None,
+            ),
+            Vec::new(),
+            Vec::new(),
+        );
+        let skipped_hash = *skipped.hash();
+
+        let error = queue.enqueue(skipped).await.unwrap_err();
+        assert!(matches!(
+            error,
+            QueueVerificationError::InvalidBlockNumber { .. }
+        ));
+
+        // The hash must be released on the eager-rejection path, or a legitimate later
+        // `enqueue` of the same block would be silently dropped by the dedup check.
+        assert!(!queue
+            .in_flight
+            .lock()
+            .expect("lock isn't poisoned")
+            .contains(&skipped_hash));
+    }
+
+    #[tokio::test]
+    async fn a_block_can_be_re_enqueued_once_it_has_been_verified() {
+        let mut queue = BlockQueue::new(SpecId::LONDON);
+
+        let genesis = genesis_block();
+        let block1 = child_block(&genesis);
+        let block1_hash = *block1.hash();
+
+        queue.enqueue(block1.clone()).await.unwrap();
+        queue.wait_for_verified().await;
+
+        let first_drain = queue.drain_verified().await;
+        assert_eq!(first_drain.len(), 1);
+
+        // Reset the linkage check so the same block can be re-submitted, as if it were being
+        // re-delivered (e.g. after a reorg). Before the `in_flight` cleanup fix, this would be
+        // silently swallowed by the dedup check instead of being verified again.
+        queue.last_enqueued = None;
+        queue.enqueue(block1).await.unwrap();
+        queue.wait_for_verified().await;
+
+        let second_drain = queue.drain_verified().await;
+        assert_eq!(second_drain.len(), 1);
+        assert_eq!(
+            *second_drain[0]
+                .as_ref()
+                .expect("block is well-formed")
+                .hash(),
+            block1_hash
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc};
+
+use rethnet_eth::{
+    block::{Block, DetailedBlock, PartialHeader},
+    receipt::BlockReceipt,
+    B256, U256,
+};
+use revm::primitives::SpecId;
+
+/// A storage solution for storing a subset of a blockchain's blocks in-memory, indexed by
+/// both block number and block hash.
+#[derive(Debug, Default)]
+pub struct SparseBlockchainStorage {
+    blocks_by_number: HashMap<U256, Arc<DetailedBlock>>,
+    blocks_by_hash: HashMap<B256, Arc<DetailedBlock>>,
+    transaction_hashes_by_block_hash: HashMap<B256, Vec<B256>>,
+    receipts_by_transaction_hash: HashMap<B256, Arc<BlockReceipt>>,
+    total_difficulties_by_hash: HashMap<B256, U256>,
+}
+
+impl SparseBlockchainStorage {
+    /// Constructs a new instance with the provided block as the only block in storage.
+    pub fn with_block(block: DetailedBlock, total_difficulty: U256) -> Self {
+        let mut storage = Self::default();
+        storage.insert_block(block, total_difficulty);
+        storage
+    }
+
+    /// Retrieves the block by hash, if it exists.
+    pub fn block_by_hash(&self, hash: &B256) -> Option<Arc<DetailedBlock>> {
+        self.blocks_by_hash.get(hash).cloned()
+    }
+
+    /// Retrieves the block by number, if it exists.
+    pub fn block_by_number(&self, number: &U256) -> Option<Arc<DetailedBlock>> {
+        self.blocks_by_number.get(number).cloned()
+    }
+
+    /// Retrieves the block that contains the provided transaction hash, if it exists.
+    pub fn block_by_transaction_hash(&self, transaction_hash: &B256) -> Option<Arc<DetailedBlock>> {
+        self.receipts_by_transaction_hash
+            .get(transaction_hash)
+            .and_then(|receipt| self.blocks_by_hash.get(&receipt.block_hash))
+            .cloned()
+    }
+
+    /// Retrieves the receipt belonging to the transaction with the provided hash, if it exists.
+    pub fn receipt_by_transaction_hash(
+        &self,
+        transaction_hash: &B256,
+    ) -> Option<Arc<BlockReceipt>> {
+        self.receipts_by_transaction_hash
+            .get(transaction_hash)
+            .cloned()
+    }
+
+    /// Retrieves the total difficulty of the block with the provided hash, if it exists.
+    pub fn total_difficulty_by_hash(&self, hash: &B256) -> Option<U256> {
+        self.total_difficulties_by_hash.get(hash).copied()
+    }
+
+    /// Inserts a block, indexing it by number and hash, and records its total difficulty.
+    pub fn insert_block(
+        &mut self,
+        block: DetailedBlock,
+        total_difficulty: U256,
+    ) -> &Arc<DetailedBlock> {
+        let block = self.index_block(block, total_difficulty);
+        let number = block.header.number;
+
+        self.blocks_by_number.insert(number, block);
+        self.blocks_by_number
+            .get(&number)
+            .expect("Block was just inserted")
+    }
+
+    /// Inserts a block by hash only, without making it part of the canonical, number-indexed
+    /// chain. Used to retain side-chain blocks so they remain reachable by
+    /// [`SparseBlockchainStorage::block_by_hash`].
+    pub fn insert_side_chain_block(
+        &mut self,
+        block: DetailedBlock,
+        total_difficulty: U256,
+    ) -> &Arc<DetailedBlock> {
+        let block = self.index_block(block, total_difficulty);
+        let hash = *block.hash();
+
+        self.blocks_by_hash.insert(hash, block);
+        self.blocks_by_hash
+            .get(&hash)
+            .expect("Block was just inserted")
+    }
+
+    /// Removes the block with the provided number from the canonical, number-indexed chain,
+    /// without removing it from the by-hash index. The block remains reachable by
+    /// [`SparseBlockchainStorage::block_by_hash`] as a side-chain block.
+    pub fn retract_canonical(&mut self, number: &U256) -> Option<Arc<DetailedBlock>> {
+        self.blocks_by_number.remove(number)
+    }
+
+    /// Makes the side-chain block with the provided hash part of the canonical, number-indexed
+    /// chain.
+    pub fn make_canonical(&mut self, hash: &B256) -> Option<Arc<DetailedBlock>> {
+        let block = self.blocks_by_hash.get(hash)?.clone();
+        self.blocks_by_number
+            .insert(block.header.number, block.clone());
+
+        Some(block)
+    }
+
+    /// Indexes a block's transactions, receipts, and total difficulty by hash, without making it
+    /// part of the canonical chain.
+    fn index_block(&mut self, block: DetailedBlock, total_difficulty: U256) -> Arc<DetailedBlock> {
+        let block = Arc::new(block);
+
+        let transaction_hashes = block
+            .transactions
+            .iter()
+            .map(|transaction| *transaction.hash())
+            .collect::<Vec<_>>();
+
+        for (transaction_hash, receipt) in transaction_hashes
+            .iter()
+            .zip(block.transaction_receipts.iter())
+        {
+            self.receipts_by_transaction_hash
+                .insert(*transaction_hash, receipt.clone());
+        }
+
+        self.transaction_hashes_by_block_hash
+            .insert(*block.hash(), transaction_hashes);
+
+        self.total_difficulties_by_hash
+            .insert(*block.hash(), total_difficulty);
+
+        self.blocks_by_hash.insert(*block.hash(), block.clone());
+
+        block
+    }
+
+    /// Removes the block with the provided number, along with its transactions and receipts,
+    /// if it exists.
+    pub fn remove_block_by_number(&mut self, number: &U256) -> Option<Arc<DetailedBlock>> {
+        let block = self.blocks_by_number.remove(number)?;
+
+        self.blocks_by_hash.remove(block.hash());
+        self.total_difficulties_by_hash.remove(block.hash());
+
+        if let Some(transaction_hashes) = self.transaction_hashes_by_block_hash.remove(block.hash())
+        {
+            for transaction_hash in transaction_hashes {
+                self.receipts_by_transaction_hash.remove(&transaction_hash);
+            }
+        }
+
+        Some(block)
+    }
+}
+
+/// Metadata describing a contiguous range of not-yet-materialized, empty blocks that can be
+/// lazily generated on demand, avoiding the cost of constructing and storing real blocks for
+/// large gaps (e.g. `hardhat_mine` with a large block count).
+#[derive(Clone, Debug)]
+struct Reservation {
+    first_number: U256,
+    last_number: U256,
+    interval: U256,
+    previous_base_fee_per_gas: Option<U256>,
+    previous_state_root: B256,
+    previous_total_difficulty: U256,
+    spec_id: SpecId,
+}
+
+/// Identifies a previously taken snapshot of a [`ReservableSparseBlockchainStorage`]'s head.
+pub type SnapshotId = u64;
+
+/// A storage solution for storing a subset of a blockchain's blocks in-memory, indexed by block
+/// number and hash, which additionally supports reserving a range of empty blocks that are
+/// materialized lazily.
+#[derive(Debug, Default)]
+pub struct ReservableSparseBlockchainStorage {
+    storage: SparseBlockchainStorage,
+    reservations: Vec<Reservation>,
+    last_block_number: U256,
+    snapshots: HashMap<SnapshotId, (U256, B256)>,
+    next_snapshot_id: SnapshotId,
+}
+
+impl ReservableSparseBlockchainStorage {
+    /// Constructs a new instance with the provided block as the only block in storage.
+    pub fn with_block(block: DetailedBlock, total_difficulty: U256) -> Self {
+        let last_block_number = block.header.number;
+
+        Self {
+            storage: SparseBlockchainStorage::with_block(block, total_difficulty),
+            reservations: Vec::new(),
+            last_block_number,
+            snapshots: HashMap::new(),
+            next_snapshot_id: 0,
+        }
+    }
+
+    /// Takes a snapshot of the current head (and any pending reservations), returning an
+    /// identifier that [`ReservableSparseBlockchainStorage::revert_to_snapshot`] can later use to
+    /// restore exactly this head, discarding any blocks inserted after the snapshot was taken.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+
+        let head_hash = self
+            .block_by_number(&self.last_block_number)
+            .map_or_else(B256::zero, |block| *block.hash());
+        self.snapshots
+            .insert(id, (self.last_block_number, head_hash));
+
+        id
+    }
+
+    /// Reverts to the head recorded by the snapshot with the provided id, discarding all blocks
+    /// inserted since. Returns whether the snapshot was known; later snapshots, whose recorded
+    /// head no longer exists after the revert, are discarded as well.
+    pub fn revert_to_snapshot(&mut self, snapshot_id: SnapshotId) -> bool {
+        let Some((block_number, head_hash)) = self.snapshots.remove(&snapshot_id) else {
+            return false;
+        };
+
+        self.snapshots
+            .retain(|_, (recorded_number, _)| *recorded_number <= block_number);
+
+        // Undo any reorg since the snapshot by making the recorded head's branch canonical again.
+        let mut hash = head_hash;
+        while let Some(block) = self.storage.block_by_hash(&hash) {
+            let number = block.header.number;
+            if self
+                .storage
+                .block_by_number(&number)
+                .map_or(false, |canonical| *canonical.hash() == hash)
+            {
+                break;
+            }
+
+            self.storage.make_canonical(&hash);
+            self.last_block_number = self.last_block_number.max(number);
+            hash = block.header.parent_hash;
+        }
+
+        self.revert_to_block(&block_number)
+    }
+
+    /// Returns the number of the last block, including reserved blocks.
+    pub fn last_block_number(&self) -> &U256 {
+        &self.last_block_number
+    }
+
+    /// Retrieves the block by hash, if it exists.
+    pub fn block_by_hash(&self, hash: &B256) -> Option<Arc<DetailedBlock>> {
+        self.storage.block_by_hash(hash)
+    }
+
+    /// Retrieves the block by number, materializing a reserved block if necessary.
+    pub fn block_by_number(&self, number: &U256) -> Option<Arc<DetailedBlock>> {
+        if let Some(block) = self.storage.block_by_number(number) {
+            return Some(block);
+        }
+
+        self.find_reservation(number)
+            .map(|reservation| Self::reserved_block(reservation, number))
+    }
+
+    /// Retrieves the block that contains the provided transaction hash, if it exists.
+    pub fn block_by_transaction_hash(&self, transaction_hash: &B256) -> Option<Arc<DetailedBlock>> {
+        self.storage.block_by_transaction_hash(transaction_hash)
+    }
+
+    /// Retrieves the receipt belonging to the transaction with the provided hash, if it exists.
+    pub fn receipt_by_transaction_hash(
+        &self,
+        transaction_hash: &B256,
+    ) -> Option<Arc<BlockReceipt>> {
+        self.storage.receipt_by_transaction_hash(transaction_hash)
+    }
+
+    /// Retrieves the total difficulty of the block with the provided hash, if it exists.
+    pub fn total_difficulty_by_hash(&self, hash: &B256) -> Option<U256> {
+        self.storage.total_difficulty_by_hash(hash)
+    }
+
+    /// Reserves the provided number of blocks, starting after the last block.
+    pub fn reserve_blocks(
+        &mut self,
+        additional: NonZeroUsize,
+        interval: U256,
+        previous_base_fee_per_gas: Option<U256>,
+        previous_state_root: B256,
+        previous_total_difficulty: U256,
+        spec_id: SpecId,
+    ) {
+        let first_number = self.last_block_number + U256::from(1);
+        let last_number = first_number + U256::from(additional.get() - 1);
+
+        self.reservations.push(Reservation {
+            first_number,
+            last_number,
+            interval,
+            previous_base_fee_per_gas,
+            previous_state_root,
+            previous_total_difficulty,
+            spec_id,
+        });
+
+        self.last_block_number = last_number;
+    }
+
+    /// Inserts a block, without validating it. The caller is responsible for ensuring that the
+    /// block's number and hash are unique, and that it is not contained in a reserved range.
+    ///
+    /// # Safety
+    ///
+    /// Ensure that the instance is not a duplicate of an existing block and that the block's
+    /// number and hash are unique.
+    pub unsafe fn insert_block_unchecked(
+        &mut self,
+        block: DetailedBlock,
+        total_difficulty: U256,
+    ) -> &Arc<DetailedBlock> {
+        if block.header.number > self.last_block_number {
+            self.last_block_number = block.header.number;
+        }
+
+        self.storage.insert_block(block, total_difficulty)
+    }
+
+    /// Inserts a block by hash only, without validating it or making it part of the canonical
+    /// chain. Inserting a block whose hash is already stored replaces the stored entries.
+    ///
+    /// # Safety
+    ///
+    /// Ensure that no different block with the same hash is stored.
+    pub unsafe fn insert_side_chain_block_unchecked(
+        &mut self,
+        block: DetailedBlock,
+        total_difficulty: U256,
+    ) -> &Arc<DetailedBlock> {
+        self.storage
+            .insert_side_chain_block(block, total_difficulty)
+    }
+
+    /// Removes the block with the provided number from the canonical chain, retaining it as a
+    /// side-chain block reachable by hash.
+    pub fn retract_canonical(&mut self, number: &U256) -> Option<Arc<DetailedBlock>> {
+        self.storage.retract_canonical(number)
+    }
+
+    /// Makes the side-chain block with the provided hash part of the canonical chain.
+    pub fn make_canonical(&mut self, hash: &B256) -> Option<Arc<DetailedBlock>> {
+        self.storage.make_canonical(hash)
+    }
+
+    /// Overwrites the number of the last canonical block, e.g. after a reorg changes the
+    /// canonical head.
+    pub fn set_last_block_number(&mut self, number: U256) {
+        self.last_block_number = number;
+    }
+
+    /// Reverts to the block with the provided number, discarding all later blocks (including
+    /// reservations). Returns whether the block number was known.
+    pub fn revert_to_block(&mut self, block_number: &U256) -> bool {
+        if block_number > &self.last_block_number {
+            return false;
+        }
+
+        if self.storage.block_by_number(block_number).is_none()
+            && self.find_reservation(block_number).is_none()
+            && *block_number != U256::ZERO
+        {
+            return false;
+        }
+
+        self.reservations
+            .retain(|reservation| reservation.first_number <= *block_number);
+
+        if let Some(reservation) = self.reservations.last_mut() {
+            if reservation.last_number > *block_number {
+                reservation.last_number = *block_number;
+            }
+        }
+
+        let mut number = *block_number + U256::from(1);
+        while let Some(_block) = self.storage.block_by_number(&number) {
+            self.storage.remove_block_by_number(&number);
+            number += U256::from(1);
+        }
+
+        self.last_block_number = *block_number;
+
+        true
+    }
+
+    fn find_reservation(&self, number: &U256) -> Option<&Reservation> {
+        self.reservations.iter().find(|reservation| {
+            *number >= reservation.first_number && *number <= reservation.last_number
+        })
+    }
+
+    /// Lazily constructs an empty block within a reserved range, reusing the state and base fee
+    /// of the block preceding the reservation.
+    fn reserved_block(reservation: &Reservation, number: &U256) -> Arc<DetailedBlock> {
+        let offset = *number - reservation.first_number + U256::from(1);
+
+        let block = Block::new(
+            PartialHeader {
+                number: *number,
+                state_root: reservation.previous_state_root,
+                base_fee: reservation.previous_base_fee_per_gas,
+                timestamp: reservation.interval * offset,
+                difficulty: if reservation.spec_id >= SpecId::MERGE {
+                    U256::ZERO
+                } else {
+                    U256::from(1)
+                },
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            if reservation.spec_id >= SpecId::SHANGHAI {
+                Some(Vec::new())
+            } else {
+                None
+            },
+        );
+
+        Arc::new(DetailedBlock::new(block, Vec::new(), Vec::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genesis_block() -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                number: U256::ZERO,
+                difficulty: U256::from(1),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    #[test]
+    fn revert_to_snapshot_discards_blocks_reserved_after_it_was_taken() {
+        let mut storage = ReservableSparseBlockchainStorage::with_block(
+            genesis_block(),
+            /* total_difficulty */ U256::from(1),
+        );
+
+        let snapshot_id = storage.snapshot();
+
+        storage.reserve_blocks(
+            NonZeroUsize::new(5).unwrap(),
+            U256::from(1),
+            None,
+            B256::zero(),
+            U256::from(1),
+            SpecId::BERLIN,
+        );
+
+        assert_eq!(*storage.last_block_number(), U256::from(5));
+        assert!(storage.block_by_number(&U256::from(3)).is_some());
+
+        assert!(storage.revert_to_snapshot(snapshot_id));
+
+        assert_eq!(*storage.last_block_number(), U256::ZERO);
+        assert!(storage.block_by_number(&U256::from(3)).is_none());
+    }
+
+    #[test]
+    fn revert_to_block_truncates_a_reservation_instead_of_discarding_it_entirely() {
+        let mut storage = ReservableSparseBlockchainStorage::with_block(
+            genesis_block(),
+            /* total_difficulty */ U256::from(1),
+        );
+
+        storage.reserve_blocks(
+            NonZeroUsize::new(5).unwrap(),
+            U256::from(1),
+            None,
+            B256::zero(),
+            U256::from(1),
+            SpecId::BERLIN,
+        );
+
+        assert!(storage.revert_to_block(&U256::from(2)));
+
+        assert_eq!(*storage.last_block_number(), U256::from(2));
+        assert!(storage.block_by_number(&U256::from(2)).is_some());
+        assert!(storage.block_by_number(&U256::from(3)).is_none());
+
+        // The truncated reservation can still be materialized up to its new bound.
+        assert!(storage.block_by_number(&U256::from(2)).is_some());
+    }
+
+    #[test]
+    fn snapshots_taken_after_a_reverted_one_are_also_discarded() {
+        let mut storage = ReservableSparseBlockchainStorage::with_block(
+            genesis_block(),
+            /* total_difficulty */ U256::from(1),
+        );
+
+        let first_snapshot = storage.snapshot();
+
+        storage.reserve_blocks(
+            NonZeroUsize::new(2).unwrap(),
+            U256::from(1),
+            None,
+            B256::zero(),
+            U256::from(1),
+            SpecId::BERLIN,
+        );
+
+        let second_snapshot = storage.snapshot();
+
+        assert!(storage.revert_to_snapshot(first_snapshot));
+
+        // `second_snapshot` recorded a head that no longer exists after the revert, so it must
+        // be treated as unknown rather than reverting to a stale block number.
+        assert!(!storage.revert_to_snapshot(second_snapshot));
+    }
+}
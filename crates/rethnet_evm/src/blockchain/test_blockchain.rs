@@ -0,0 +1,633 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use rethnet_eth::{
+    block::{Block, DetailedBlock, PartialHeader},
+    receipt::BlockReceipt,
+    Bytes, B256, U256,
+};
+use revm::db::BlockHashRef;
+
+use super::{Blockchain, BlockchainError, BlockchainMut, ImportRoute, SnapshotId};
+
+/// An in-memory test double for the [`Blockchain`]/[`BlockchainMut`] traits, backed by plain
+/// `HashMap`s instead of a real [`super::LocalBlockchain`]. Intended for exercising code that is
+/// generic over these traits without needing valid RLP blocks or an initialized state, and for
+/// deterministically triggering error paths via [`TestBlockchain::fail_next`].
+#[derive(Debug)]
+pub struct TestBlockchain {
+    chain_id: U256,
+    blocks_by_number: HashMap<U256, Arc<DetailedBlock>>,
+    blocks_by_hash: HashMap<B256, Arc<DetailedBlock>>,
+    receipts_by_transaction_hash: HashMap<B256, Arc<BlockReceipt>>,
+    total_difficulties_by_hash: HashMap<B256, U256>,
+    last_block_number: U256,
+    snapshots: HashMap<SnapshotId, (U256, B256)>,
+    next_snapshot_id: SnapshotId,
+    next_failure: Mutex<Option<BlockchainError>>,
+}
+
+impl TestBlockchain {
+    /// Constructs a new instance with the provided block as the only (genesis) block.
+    pub fn new(chain_id: U256, genesis_block: DetailedBlock) -> Self {
+        let last_block_number = genesis_block.header.number;
+        let genesis_block = Arc::new(genesis_block);
+
+        let mut total_difficulties_by_hash = HashMap::new();
+        total_difficulties_by_hash.insert(*genesis_block.hash(), genesis_block.header.difficulty);
+
+        let mut blocks_by_number = HashMap::new();
+        blocks_by_number.insert(last_block_number, genesis_block.clone());
+
+        let mut blocks_by_hash = HashMap::new();
+        blocks_by_hash.insert(*genesis_block.hash(), genesis_block);
+
+        Self {
+            chain_id,
+            blocks_by_number,
+            blocks_by_hash,
+            receipts_by_transaction_hash: HashMap::new(),
+            total_difficulties_by_hash,
+            last_block_number,
+            snapshots: HashMap::new(),
+            next_snapshot_id: 0,
+            next_failure: Mutex::new(None),
+        }
+    }
+
+    /// Appends `count` empty blocks on top of the current head, spacing their timestamps by
+    /// `interval`, and returns `self` for further chaining.
+    pub fn add_blocks(mut self, count: usize, interval: U256) -> Self {
+        for _ in 0..count {
+            let parent = self
+                .blocks_by_number
+                .get(&self.last_block_number)
+                .expect("Head block must exist")
+                .clone();
+
+            let number = parent.header.number + U256::from(1);
+            let block = Block::new(
+                PartialHeader {
+                    parent_hash: *parent.hash(),
+                    number,
+                    timestamp: parent.header.timestamp + interval,
+                    ..PartialHeader::default()
+                },
+                Vec::new(),
+                Vec::new(),
+                None,
+            );
+            let block = Arc::new(DetailedBlock::new(block, Vec::new(), Vec::new()));
+
+            let total_difficulty = self
+                .total_difficulties_by_hash
+                .get(parent.hash())
+                .copied()
+                .unwrap_or_default();
+
+            self.total_difficulties_by_hash
+                .insert(*block.hash(), total_difficulty);
+            self.blocks_by_hash.insert(*block.hash(), block.clone());
+            self.blocks_by_number.insert(number, block);
+            self.last_block_number = number;
+        }
+
+        self
+    }
+
+    /// Registers the provided receipt as belonging to the transaction with the given hash, and
+    /// returns `self` for further chaining.
+    pub fn set_receipt_for(mut self, transaction_hash: B256, receipt: Arc<BlockReceipt>) -> Self {
+        self.receipts_by_transaction_hash
+            .insert(transaction_hash, receipt);
+
+        self
+    }
+
+    /// Overwrites the total difficulty recorded for the block with the given hash, and returns
+    /// `self` for further chaining.
+    pub fn set_total_difficulty(mut self, hash: B256, total_difficulty: U256) -> Self {
+        self.total_difficulties_by_hash
+            .insert(hash, total_difficulty);
+
+        self
+    }
+
+    /// Arranges for the next call to a fallible [`Blockchain`], [`BlockchainMut`] or
+    /// [`BlockHashRef`] trait method to return the provided error instead of performing its
+    /// normal lookup/mutation, and returns `self` for further chaining. `chain_id`,
+    /// `last_block_number` and `snapshot` cannot fail and leave the error pending.
+    pub fn fail_next(self, error: BlockchainError) -> Self {
+        *self.next_failure.lock().expect("lock isn't poisoned") = Some(error);
+
+        self
+    }
+
+    fn take_failure(&self) -> Option<BlockchainError> {
+        self.next_failure
+            .lock()
+            .expect("lock isn't poisoned")
+            .take()
+    }
+
+    /// Computes the blocks retracted from and enacted onto the canonical chain when switching
+    /// its head from `from` to `to`, mirroring [`super::LocalBlockchain::tree_route`].
+    fn tree_route(
+        &self,
+        from: &B256,
+        to: &B256,
+    ) -> (Vec<Arc<DetailedBlock>>, Vec<Arc<DetailedBlock>>) {
+        let mut from_block = self.blocks_by_hash.get(from).expect("must exist").clone();
+        let mut to_block = self.blocks_by_hash.get(to).expect("must exist").clone();
+
+        let mut retracted = Vec::new();
+        let mut enacted = Vec::new();
+
+        while from_block.header.number > to_block.header.number {
+            let parent = self
+                .blocks_by_hash
+                .get(&from_block.header.parent_hash)
+                .expect("parent of a stored block must be stored")
+                .clone();
+            retracted.push(std::mem::replace(&mut from_block, parent));
+        }
+
+        while to_block.header.number > from_block.header.number {
+            let parent = self
+                .blocks_by_hash
+                .get(&to_block.header.parent_hash)
+                .expect("parent of a stored block must be stored")
+                .clone();
+            enacted.push(std::mem::replace(&mut to_block, parent));
+        }
+
+        while from_block.hash() != to_block.hash() {
+            let from_parent = self
+                .blocks_by_hash
+                .get(&from_block.header.parent_hash)
+                .expect("parent of a stored block must be stored")
+                .clone();
+            retracted.push(std::mem::replace(&mut from_block, from_parent));
+
+            let to_parent = self
+                .blocks_by_hash
+                .get(&to_block.header.parent_hash)
+                .expect("parent of a stored block must be stored")
+                .clone();
+            enacted.push(std::mem::replace(&mut to_block, to_parent));
+        }
+
+        enacted.reverse();
+
+        (retracted, enacted)
+    }
+}
+
+#[async_trait]
+impl Blockchain for TestBlockchain {
+    type Error = BlockchainError;
+
+    async fn block_by_hash(&self, hash: &B256) -> Result<Option<Arc<DetailedBlock>>, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(self.blocks_by_hash.get(hash).cloned())
+    }
+
+    async fn block_by_number(
+        &self,
+        number: &U256,
+    ) -> Result<Option<Arc<DetailedBlock>>, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(self.blocks_by_number.get(number).cloned())
+    }
+
+    async fn block_by_transaction_hash(
+        &self,
+        transaction_hash: &B256,
+    ) -> Result<Option<Arc<DetailedBlock>>, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(self
+            .receipts_by_transaction_hash
+            .get(transaction_hash)
+            .and_then(|receipt| self.blocks_by_hash.get(&receipt.block_hash))
+            .cloned())
+    }
+
+    async fn block_supports_spec(
+        &self,
+        _number: &U256,
+        _spec_id: revm::primitives::SpecId,
+    ) -> Result<bool, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(true)
+    }
+
+    async fn chain_id(&self) -> U256 {
+        self.chain_id
+    }
+
+    async fn last_block(&self) -> Result<Arc<DetailedBlock>, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(self
+            .blocks_by_number
+            .get(&self.last_block_number)
+            .expect("Head block must exist")
+            .clone())
+    }
+
+    async fn last_block_number(&self) -> U256 {
+        self.last_block_number
+    }
+
+    async fn receipt_by_transaction_hash(
+        &self,
+        transaction_hash: &B256,
+    ) -> Result<Option<Arc<BlockReceipt>>, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(self
+            .receipts_by_transaction_hash
+            .get(transaction_hash)
+            .cloned())
+    }
+
+    async fn total_difficulty_by_hash(&self, hash: &B256) -> Result<Option<U256>, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(self.total_difficulties_by_hash.get(hash).copied())
+    }
+}
+
+#[async_trait]
+impl BlockchainMut for TestBlockchain {
+    type Error = BlockchainError;
+
+    async fn insert_block(&mut self, block: DetailedBlock) -> Result<ImportRoute, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        let total_difficulty = self
+            .total_difficulties_by_hash
+            .get(&block.header.parent_hash)
+            .copied()
+            .unwrap_or_default()
+            + block.header.difficulty;
+
+        let block_hash = *block.hash();
+        let block = Arc::new(block);
+
+        // Every block is indexed by hash as soon as it's known, canonical or not, mirroring
+        // `super::LocalBlockchain`'s split between `blocks_by_hash` (all known blocks) and
+        // `blocks_by_number` (canonical only).
+        self.total_difficulties_by_hash
+            .insert(block_hash, total_difficulty);
+        self.blocks_by_hash.insert(block_hash, block.clone());
+
+        let current_head = self
+            .blocks_by_number
+            .get(&self.last_block_number)
+            .expect("Head block must exist")
+            .clone();
+
+        let current_head_total_difficulty = self
+            .total_difficulties_by_hash
+            .get(current_head.hash())
+            .copied()
+            .expect("Head block's total difficulty must be known");
+
+        if current_head.hash() == &block_hash || total_difficulty <= current_head_total_difficulty {
+            return Ok(ImportRoute {
+                retracted: Vec::new(),
+                enacted: Vec::new(),
+            });
+        }
+
+        let (retracted, enacted) = self.tree_route(current_head.hash(), &block_hash);
+
+        for retracted_block in &retracted {
+            self.blocks_by_number.remove(&retracted_block.header.number);
+        }
+
+        for enacted_block in &enacted {
+            self.blocks_by_number
+                .insert(enacted_block.header.number, enacted_block.clone());
+        }
+
+        self.last_block_number = enacted
+            .last()
+            .expect("a heavier chain always enacts at least one block")
+            .header
+            .number;
+
+        Ok(ImportRoute { retracted, enacted })
+    }
+
+    async fn reserve_blocks(
+        &mut self,
+        _additional: usize,
+        _interval: U256,
+    ) -> Result<(), Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    async fn revert_to_block(&mut self, block_number: &U256) -> Result<(), Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        if block_number > &self.last_block_number
+            || !self.blocks_by_number.contains_key(block_number)
+        {
+            return Err(BlockchainError::UnknownBlockNumber);
+        }
+
+        let mut number = *block_number + U256::from(1);
+        while let Some(block) = self.blocks_by_number.remove(&number) {
+            self.blocks_by_hash.remove(block.hash());
+            number += U256::from(1);
+        }
+
+        self.last_block_number = *block_number;
+
+        Ok(())
+    }
+
+    async fn snapshot(&mut self) -> SnapshotId {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+
+        let head_hash = *self
+            .blocks_by_number
+            .get(&self.last_block_number)
+            .expect("Head block must exist")
+            .hash();
+        self.snapshots
+            .insert(id, (self.last_block_number, head_hash));
+
+        id
+    }
+
+    async fn revert_to_snapshot(&mut self, snapshot_id: SnapshotId) -> Result<(), Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        let Some((block_number, head_hash)) = self.snapshots.remove(&snapshot_id) else {
+            return Err(BlockchainError::UnknownSnapshot);
+        };
+
+        self.snapshots
+            .retain(|_, (recorded_number, _)| *recorded_number <= block_number);
+
+        // Undo any reorg since the snapshot by making the recorded head's branch canonical again.
+        let mut hash = head_hash;
+        while let Some(block) = self.blocks_by_hash.get(&hash).cloned() {
+            let number = block.header.number;
+            if self
+                .blocks_by_number
+                .get(&number)
+                .map_or(false, |canonical| *canonical.hash() == hash)
+            {
+                break;
+            }
+
+            self.blocks_by_number.insert(number, block.clone());
+            self.last_block_number = self.last_block_number.max(number);
+            hash = block.header.parent_hash;
+        }
+
+        self.revert_to_block(&block_number).await
+    }
+}
+
+impl BlockHashRef for TestBlockchain {
+    type Error = BlockchainError;
+
+    fn block_hash(&self, number: U256) -> Result<B256, Self::Error> {
+        if let Some(error) = self.take_failure() {
+            return Err(error);
+        }
+
+        self.blocks_by_number
+            .get(&number)
+            .map(|block| *block.hash())
+            .ok_or(BlockchainError::UnknownBlockNumber)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genesis_block() -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                number: U256::ZERO,
+                difficulty: U256::from(1),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    fn child_block(parent: &DetailedBlock, difficulty: U256, extra_data: &[u8]) -> DetailedBlock {
+        let block = Block::new(
+            PartialHeader {
+                parent_hash: *parent.hash(),
+                number: parent.header.number + U256::from(1),
+                difficulty,
+                extra_data: Bytes::from(extra_data.to_vec()),
+                ..PartialHeader::default()
+            },
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        DetailedBlock::new(block, Vec::new(), Vec::new())
+    }
+
+    #[tokio::test]
+    async fn insert_block_reorgs_to_heavier_side_chain() {
+        let genesis = genesis_block();
+        let mut blockchain = TestBlockchain::new(U256::from(1), genesis.clone());
+
+        let light = child_block(&genesis, U256::from(1), b"light");
+        let light_hash = *light.hash();
+        blockchain.insert_block(light).await.unwrap();
+
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &light_hash);
+
+        let heavy = child_block(&genesis, U256::from(2), b"heavy");
+        let heavy_hash = *heavy.hash();
+        let route = blockchain.insert_block(heavy).await.unwrap();
+
+        assert_eq!(route.retracted.len(), 1);
+        assert_eq!(route.retracted[0].hash(), &light_hash);
+        assert_eq!(route.enacted.len(), 1);
+        assert_eq!(route.enacted[0].hash(), &heavy_hash);
+
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &heavy_hash);
+        assert_eq!(
+            blockchain
+                .block_by_number(&U256::from(1))
+                .await
+                .unwrap()
+                .unwrap()
+                .hash(),
+            &heavy_hash
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_block_keeps_tied_side_chain_off_the_canonical_chain() {
+        let genesis = genesis_block();
+        let mut blockchain = TestBlockchain::new(U256::from(1), genesis.clone());
+
+        let first = child_block(&genesis, U256::from(2), b"first");
+        let first_hash = *first.hash();
+        blockchain.insert_block(first).await.unwrap();
+
+        let tied = child_block(&genesis, U256::from(2), b"tied");
+        let route = blockchain.insert_block(tied).await.unwrap();
+
+        assert!(route.retracted.is_empty());
+        assert!(route.enacted.is_empty());
+        assert_eq!(blockchain.last_block().await.unwrap().hash(), &first_hash);
+    }
+
+    #[tokio::test]
+    async fn insert_block_short_circuits_on_duplicate_head() {
+        let genesis = genesis_block();
+        let mut blockchain = TestBlockchain::new(U256::from(1), genesis.clone());
+
+        let block = child_block(&genesis, U256::from(1), b"only");
+        blockchain.insert_block(block.clone()).await.unwrap();
+
+        let route = blockchain.insert_block(block).await.unwrap();
+
+        assert!(route.retracted.is_empty());
+        assert!(route.enacted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fail_next_short_circuits_the_next_trait_call_only() {
+        let genesis = genesis_block();
+        let mut blockchain = TestBlockchain::new(U256::from(1), genesis)
+            .fail_next(BlockchainError::UnknownBlockHash);
+
+        let error = blockchain.last_block().await.unwrap_err();
+        assert!(matches!(error, BlockchainError::UnknownBlockHash));
+
+        // The failure is consumed by the first call; subsequent calls go through normally.
+        blockchain.last_block().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn set_receipt_for_and_set_total_difficulty_are_reflected_by_their_getters() {
+        let genesis = genesis_block();
+        let genesis_hash = *genesis.hash();
+
+        let transaction_hash = B256::zero();
+        let receipt = Arc::new(BlockReceipt {
+            block_hash: genesis_hash,
+            ..BlockReceipt::default()
+        });
+
+        let blockchain = TestBlockchain::new(U256::from(1), genesis)
+            .set_receipt_for(transaction_hash, receipt.clone())
+            .set_total_difficulty(genesis_hash, U256::from(42));
+
+        assert_eq!(
+            blockchain
+                .receipt_by_transaction_hash(&transaction_hash)
+                .await
+                .unwrap()
+                .map(|receipt| receipt.block_hash),
+            Some(genesis_hash)
+        );
+        assert_eq!(
+            blockchain
+                .total_difficulty_by_hash(&genesis_hash)
+                .await
+                .unwrap(),
+            Some(U256::from(42))
+        );
+    }
+
+    #[tokio::test]
+    async fn revert_to_block_discards_blocks_after_the_given_number() {
+        let genesis = genesis_block();
+        let mut blockchain =
+            TestBlockchain::new(U256::from(1), genesis).add_blocks(3, U256::from(1));
+
+        blockchain.revert_to_block(&U256::from(1)).await.unwrap();
+
+        assert_eq!(blockchain.last_block_number().await, U256::from(1));
+        assert!(blockchain
+            .block_by_number(&U256::from(2))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn revert_to_snapshot_discards_blocks_inserted_after_the_snapshot() {
+        let genesis = genesis_block();
+        let mut blockchain =
+            TestBlockchain::new(U256::from(1), genesis).add_blocks(1, U256::from(1));
+
+        let snapshot_id = blockchain.snapshot().await;
+
+        blockchain = blockchain.add_blocks(2, U256::from(1));
+        assert_eq!(blockchain.last_block_number().await, U256::from(3));
+
+        blockchain.revert_to_snapshot(snapshot_id).await.unwrap();
+
+        assert_eq!(blockchain.last_block_number().await, U256::from(1));
+        assert!(blockchain
+            .block_by_number(&U256::from(2))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn revert_to_snapshot_fails_for_an_unknown_snapshot() {
+        let mut blockchain = TestBlockchain::new(U256::from(1), genesis_block());
+
+        let error = blockchain.revert_to_snapshot(12345).await.unwrap_err();
+
+        assert!(matches!(error, BlockchainError::UnknownSnapshot));
+    }
+}
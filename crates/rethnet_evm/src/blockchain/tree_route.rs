@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use rethnet_eth::{block::DetailedBlock, B256};
+
+/// The route connecting two blocks, computed by walking both chains back towards their common
+/// ancestor. Modeled on OpenEthereum's `TreeRoute`.
+#[derive(Debug)]
+pub struct TreeRoute {
+    /// The blocks between `from` and the common ancestor (exclusive), ordered from `from` down
+    /// towards the common ancestor. These are no longer part of the canonical chain once `to`
+    /// is enacted.
+    pub retracted: Vec<Arc<DetailedBlock>>,
+    /// The hash of the common ancestor of `from` and `to`.
+    pub common_ancestor: B256,
+    /// The blocks between the common ancestor (exclusive) and `to`, ordered from just after the
+    /// common ancestor up to `to`.
+    pub enacted: Vec<Arc<DetailedBlock>>,
+}
+
+/// The result of importing a block that changed the canonical chain.
+#[derive(Debug)]
+pub struct ImportRoute {
+    /// The blocks that are no longer part of the canonical chain, ordered from the old head
+    /// down towards the common ancestor.
+    pub retracted: Vec<Arc<DetailedBlock>>,
+    /// The blocks that are now part of the canonical chain, ordered from just after the common
+    /// ancestor up to the new head.
+    pub enacted: Vec<Arc<DetailedBlock>>,
+}
+
+impl From<TreeRoute> for ImportRoute {
+    fn from(route: TreeRoute) -> Self {
+        Self {
+            retracted: route.retracted,
+            enacted: route.enacted,
+        }
+    }
+}